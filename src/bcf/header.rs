@@ -4,7 +4,10 @@
 // except according to those terms.
 
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::slice;
+use std::str;
 use std::ffi;
 
 use htslib;
@@ -13,6 +16,121 @@ use htslib;
 pub type SampleSubset = Vec<i32>;
 
 
+thread_local! {
+    /// Per-header cache of the `BCF_DT_ID` name -> id dictionary, keyed by
+    /// the `bcf_hdr_t` pointer it was built from.
+    ///
+    /// `HeaderView::name_to_id` backs `Record::push_filter`/`set_filters`,
+    /// which an annotation loop calls once per record per filter; without
+    /// caching, a cohort header declaring hundreds to thousands of
+    /// FILTER/INFO/FORMAT ids would turn every filter update into an
+    /// `O(header_size)` scan. The entry is rebuilt if the dictionary's size
+    /// changes and dropped by `forget_id_cache` when the owning
+    /// `Reader`/`Writer` destroys its header.
+    static NAME_TO_ID_CACHE: RefCell<HashMap<usize, (usize, HashMap<Vec<u8>, i32>)>> = RefCell::new(HashMap::new());
+}
+
+
+/// Drop any cached `name_to_id` dictionary for `header`. Must be called
+/// before `bcf_hdr_destroy` so a later, unrelated header allocated at the
+/// same address can't pick up a stale entry.
+pub(crate) fn forget_id_cache(header: *mut htslib::vcf::bcf_hdr_t) {
+    NAME_TO_ID_CACHE.with(|cache| { cache.borrow_mut().remove(&(header as usize)); });
+}
+
+
+/// The `Number` entry of an INFO or FORMAT header line, describing how many
+/// values are expected per record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Number {
+    /// One value per alternate allele (`Number=A`).
+    Alleles,
+    /// One value for each possible allele, including the reference (`Number=R`).
+    Ref,
+    /// One value for each possible genotype (`Number=G`).
+    Genotypes,
+    /// The number of values varies, is unknown or unbounded (`Number=.`).
+    Unknown,
+    /// A fixed number of values.
+    Fixed(u32),
+}
+
+
+impl Number {
+    fn parse(value: &[u8]) -> Self {
+        match value {
+            b"A" => Number::Alleles,
+            b"R" => Number::Ref,
+            b"G" => Number::Genotypes,
+            b"." => Number::Unknown,
+            _    => str::from_utf8(value).ok()
+                        .and_then(|v| v.parse().ok())
+                        .map(Number::Fixed)
+                        .unwrap_or(Number::Unknown),
+        }
+    }
+}
+
+
+/// The `Type` entry of an INFO or FORMAT header line, describing the BCF
+/// value type the tag is encoded with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagType {
+    Integer,
+    Float,
+    String,
+    Flag,
+}
+
+
+impl TagType {
+    fn parse(value: &[u8]) -> Option<Self> {
+        match value {
+            b"Integer" => Some(TagType::Integer),
+            b"Float"   => Some(TagType::Float),
+            b"String"  => Some(TagType::String),
+            b"Flag"    => Some(TagType::Flag),
+            _          => None,
+        }
+    }
+}
+
+
+/// The declared shape of an INFO or FORMAT tag, as found in the header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagDescription {
+    pub number: Number,
+    pub tag_type: TagType,
+    pub description: String,
+}
+
+
+/// The declared description of a FILTER, as found in the header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterDescription {
+    pub description: String,
+}
+
+
+/// A contig declared in the header via a `##contig` line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contig {
+    pub name: String,
+    pub length: Option<u64>,
+}
+
+
+fn unquote(value: &[u8]) -> String {
+    let value = if value.len() >= 2 && value[0] == b'"' && value[value.len() - 1] == b'"' {
+        &value[1..value.len() - 1]
+    }
+    else {
+        value
+    };
+    String::from_utf8_lossy(value).into_owned()
+}
+
+
 /// A BCF header.
 pub struct Header {
     pub inner: *mut htslib::vcf::bcf_hdr_t,
@@ -106,4 +224,106 @@ impl HeaderView {
         let names = unsafe { slice::from_raw_parts(self.inner().samples, self.sample_count() as usize) };
         names.iter().map(|name| unsafe { ffi::CStr::from_ptr(*name).to_bytes() }).collect()
     }
+
+    /// Look up the dictionary id of `id` within `BCF_DT_ID` (the shared
+    /// FILTER/INFO/FORMAT namespace). Returns `None` if `id` is not declared
+    /// in the header at all.
+    ///
+    /// `bcf_hdr_id2int`/`bcf_hdr_int2id` are `static inline` in htslib's
+    /// `vcf.h` and are not guaranteed to be linkable symbols, so this builds
+    /// a name -> id map from the header's own `id[BCF_DT_ID]` dictionary
+    /// array instead, which is a plain exported struct field. The map is
+    /// cached per header (see `NAME_TO_ID_CACHE`) so repeated lookups
+    /// against the same header, as done by `Record::push_filter`/
+    /// `set_filters`, are O(1) rather than rescanning the array each call.
+    pub fn name_to_id(&self, id: &[u8]) -> Option<i32> {
+        let inner = self.inner();
+        let n = inner.n[htslib::vcf::BCF_DT_ID as usize] as usize;
+        let key = self.inner as usize;
+        NAME_TO_ID_CACHE.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            let stale = cache.get(&key).map(|&(cached_n, _)| cached_n != n).unwrap_or(true);
+            if stale {
+                let dict = unsafe { slice::from_raw_parts(inner.id[htslib::vcf::BCF_DT_ID as usize], n) };
+                let names = dict.iter().enumerate()
+                    .map(|(i, pair)| (unsafe { ffi::CStr::from_ptr(pair.key).to_bytes() }.to_owned(), i as i32))
+                    .collect();
+                cache.insert(key, (n, names));
+            }
+            cache[&key].1.get(id).cloned()
+        })
+    }
+
+    /// Resolve the dictionary id `int_id` back to its textual name. Returns
+    /// `None` if `int_id` is negative or outside the `BCF_DT_ID` dictionary,
+    /// e.g. a stale id from a header that was subset or rebuilt.
+    pub fn id_to_name(&self, int_id: i32) -> Option<Vec<u8>> {
+        let inner = self.inner();
+        let n = inner.n[htslib::vcf::BCF_DT_ID as usize];
+        if int_id < 0 || int_id >= n {
+            return None;
+        }
+        let pair = unsafe { *inner.id[htslib::vcf::BCF_DT_ID as usize].offset(int_id as isize) };
+        Some(unsafe { ffi::CStr::from_ptr(pair.key).to_bytes() }.to_owned())
+    }
+
+    fn header_record<'a>(&'a self, line_type: i32, id: &[u8]) -> Option<&'a htslib::vcf::bcf_hrec_t> {
+        let inner = self.inner();
+        let hrecs = unsafe { slice::from_raw_parts(inner.hrec, inner.nhrec as usize) };
+        hrecs.iter()
+             .filter_map(|&hrec| unsafe { hrec.as_ref() })
+             .find(|hrec| hrec.type_ == line_type && Self::hrec_value(hrec, b"ID").map(|v| &v[..] == id).unwrap_or(false))
+    }
+
+    fn hrec_value(hrec: &htslib::vcf::bcf_hrec_t, key: &[u8]) -> Option<Vec<u8>> {
+        let keys = unsafe { slice::from_raw_parts(hrec.keys, hrec.nkeys as usize) };
+        let vals = unsafe { slice::from_raw_parts(hrec.vals, hrec.nkeys as usize) };
+        keys.iter().zip(vals.iter()).find(|&(&k, _)| unsafe { ffi::CStr::from_ptr(k).to_bytes() } == key)
+            .map(|(_, &v)| unsafe { ffi::CStr::from_ptr(v).to_bytes() }.to_owned())
+    }
+
+    fn tag_description(&self, line_type: i32, tag: &[u8]) -> Option<TagDescription> {
+        self.header_record(line_type, tag).and_then(|hrec| {
+            let number = Self::hrec_value(hrec, b"Number").map(|v| Number::parse(&v)).unwrap_or(Number::Unknown);
+            let tag_type = match Self::hrec_value(hrec, b"Type").and_then(|v| TagType::parse(&v)) {
+                Some(tag_type) => tag_type,
+                None           => return None,
+            };
+            let description = Self::hrec_value(hrec, b"Description").map(|v| unquote(&v)).unwrap_or_default();
+            Some(TagDescription { number: number, tag_type: tag_type, description: description })
+        })
+    }
+
+    /// Look up the declared `Number` and `Type` of an INFO tag.
+    pub fn info_type(&self, tag: &[u8]) -> Option<TagDescription> {
+        self.tag_description(htslib::vcf::BCF_HL_INFO, tag)
+    }
+
+    /// Look up the declared `Number` and `Type` of a FORMAT tag.
+    pub fn format_type(&self, tag: &[u8]) -> Option<TagDescription> {
+        self.tag_description(htslib::vcf::BCF_HL_FMT, tag)
+    }
+
+    /// Look up the description of a FILTER declared via `id`.
+    pub fn filter(&self, id: &[u8]) -> Option<FilterDescription> {
+        self.header_record(htslib::vcf::BCF_HL_FLT, id).map(|hrec| {
+            FilterDescription { description: Self::hrec_value(hrec, b"Description").map(|v| unquote(&v)).unwrap_or_default() }
+        })
+    }
+
+    /// List the contigs declared in the header, in header order.
+    pub fn contigs(&self) -> Vec<Contig> {
+        let inner = self.inner();
+        let hrecs = unsafe { slice::from_raw_parts(inner.hrec, inner.nhrec as usize) };
+        hrecs.iter()
+             .filter_map(|&hrec| unsafe { hrec.as_ref() })
+             .filter(|hrec| hrec.type_ == htslib::vcf::BCF_HL_CTG)
+             .map(|hrec| {
+                 let name = Self::hrec_value(hrec, b"ID").map(|v| String::from_utf8_lossy(&v).into_owned()).unwrap_or_default();
+                 let length = Self::hrec_value(hrec, b"length")
+                     .and_then(|v| str::from_utf8(&v).ok().and_then(|v| v.parse().ok()));
+                 Contig { name: name, length: length }
+             })
+             .collect()
+    }
 }
@@ -0,0 +1,143 @@
+// Copyright 2014 Johannes Köster.
+// Licensed under the MIT license (http://opensource.org/licenses/MIT)
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Bridges an arbitrary Rust `Read`/`Write` stream into htslib's `hFILE`
+//! abstraction, so `Reader`/`Writer` can operate on stdin/stdout or any
+//! other byte stream (e.g. a gzip decoder wrapped around a `File`)
+//! instead of only filesystem paths.
+
+use std::ffi;
+use std::io::{Read, Write};
+use std::mem;
+use std::os::raw::{c_int, c_void};
+use std::panic;
+use std::ptr;
+use std::slice;
+
+use htslib;
+
+
+/// An `hFILE` that carries a Rust stream inline after the htslib base
+/// header, which comes first so htslib can treat it as a plain `hFILE`.
+/// Only the `hFILE_backend` callback table is boxed.
+#[repr(C)]
+struct RHFile<T> {
+    base: htslib::hts::hFILE,
+    stream: T,
+}
+
+
+/// A panic from user-supplied `Read`/`Write`/`Drop` code (e.g. a gzip
+/// decoder choking on a truncated or malformed stream) must never unwind
+/// across this `extern "C"` boundary into htslib, which would abort the
+/// process instead of returning a clean I/O error. Catch it and translate
+/// it into the failure return the caller expects.
+unsafe extern "C" fn read_cb<T: Read>(fp: *mut htslib::hts::hFILE, buffer: *mut c_void, nbytes: usize) -> isize {
+    let fp = fp as *mut RHFile<T>;
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        let buf = slice::from_raw_parts_mut(buffer as *mut u8, nbytes);
+        (*fp).stream.read(buf)
+    }));
+    match result {
+        Ok(Ok(n)) => n as isize,
+        Ok(Err(_)) | Err(_) => -1,
+    }
+}
+
+
+unsafe extern "C" fn write_cb<T: Write>(fp: *mut htslib::hts::hFILE, buffer: *const c_void, nbytes: usize) -> isize {
+    let fp = fp as *mut RHFile<T>;
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        let buf = slice::from_raw_parts(buffer as *const u8, nbytes);
+        (*fp).stream.write(buf)
+    }));
+    match result {
+        Ok(Ok(n)) => n as isize,
+        Ok(Err(_)) | Err(_) => -1,
+    }
+}
+
+
+unsafe extern "C" fn flush_cb<T: Write>(fp: *mut htslib::hts::hFILE) -> c_int {
+    let fp = fp as *mut RHFile<T>;
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| (*fp).stream.flush()));
+    match result {
+        Ok(Ok(())) => 0,
+        Ok(Err(_)) | Err(_) => -1,
+    }
+}
+
+
+unsafe extern "C" fn close_cb<T>(fp: *mut htslib::hts::hFILE) -> c_int {
+    let fp = fp as *mut RHFile<T>;
+    // Free the backend box regardless of whether dropping the stream
+    // panics, so a malformed stream can't also leak the vtable.
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| ptr::drop_in_place(&mut (*fp).stream)));
+    drop(Box::from_raw((*fp).base.backend as *mut htslib::hts::hFILE_backend));
+    match result {
+        Ok(())  => 0,
+        Err(_) => -1,
+    }
+}
+
+
+fn wrap<T>(stream: T, mode: &[u8], backend: htslib::hts::hFILE_backend) -> *mut htslib::hts::hFILE {
+    let c_mode = ffi::CString::new(mode).unwrap();
+    unsafe {
+        let fp = htslib::hts::hfile_init(mem::size_of::<RHFile<T>>(), c_mode.as_ptr(), 0) as *mut RHFile<T>;
+        assert!(!fp.is_null(), "hfile_init failed");
+        ptr::write(&mut (*fp).stream, stream);
+        (*fp).base.backend = Box::into_raw(Box::new(backend));
+        fp as *mut htslib::hts::hFILE
+    }
+}
+
+
+/// Wrap a `Read` stream as an `hFILE` opened for reading.
+pub fn from_reader<R: Read + Send + 'static>(stream: R) -> *mut htslib::hts::hFILE {
+    wrap(stream, b"r", htslib::hts::hFILE_backend {
+        read: Some(read_cb::<R>),
+        write: None,
+        seek: None,
+        flush: None,
+        close: Some(close_cb::<R>),
+    })
+}
+
+
+/// Wrap a `Write` stream as an `hFILE` opened for writing.
+pub fn from_writer<W: Write + Send + 'static>(stream: W) -> *mut htslib::hts::hFILE {
+    wrap(stream, b"w", htslib::hts::hFILE_backend {
+        read: None,
+        write: Some(write_cb::<W>),
+        seek: None,
+        flush: Some(flush_cb::<W>),
+        close: Some(close_cb::<W>),
+    })
+}
+
+
+/// Open an `hFILE` as an `htsFile`. In read mode the underlying format
+/// (BCF, VCF, or gzipped VCF) is auto-detected from its contents; in write
+/// mode it is given by `mode`.
+///
+/// Returns `None` if htslib cannot open the stream in `mode`, e.g. when a
+/// stream opened for reading doesn't parse as a recognised format (bad
+/// magic, truncated input, unsupported compression) — the kind of failure
+/// an arbitrary/untrusted stream can always produce. On that path the
+/// `hFILE` (and the boxed `hFILE_backend`/Rust stream it owns) is closed
+/// here, since `hts_hopen` does not take ownership of it on failure.
+pub fn hts_open(hfile: *mut htslib::hts::hFILE, mode: &[u8]) -> Option<*mut htslib::vcf::htsFile> {
+    let c_mode = ffi::CString::new(mode).unwrap();
+    let name = ffi::CString::new("-").unwrap();
+    let htsfile = unsafe { htslib::vcf::hts_hopen(hfile, name.as_ptr(), c_mode.as_ptr()) };
+    if htsfile.is_null() {
+        unsafe { htslib::hts::hclose(hfile) };
+        None
+    }
+    else {
+        Some(htsfile)
+    }
+}
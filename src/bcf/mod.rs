@@ -1,13 +1,16 @@
 
 use std::ffi;
 use std::convert::AsRef;
+use std::io;
+use std::io::{Read, Write};
 use std::path::Path;
 
 pub mod record;
 pub mod header;
+mod hfile;
 
 use htslib;
-use bcf::header::{HeaderView, SampleSubset};
+use bcf::header::{self, HeaderView, SampleSubset};
 
 pub use bcf::header::Header;
 pub use bcf::record::Record;
@@ -25,10 +28,41 @@ unsafe impl Send for Reader {}
 impl Reader {
    pub fn new<P: AsRef<Path>>(path: &P) -> Self {
         let htsfile = bcf_open(path, b"r");
+        Self::from_htsfile(htsfile)
+    }
+
+    /// Read from standard input instead of a file.
+    pub fn from_stdin() -> Self {
+        Self::from_reader(io::stdin())
+    }
+
+    /// Read BCF/VCF from an arbitrary byte stream (e.g. a gzip decoder
+    /// wrapped around a `File`, or a network stream), without needing a
+    /// temporary file.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stream doesn't parse as a recognised BCF/VCF format
+    /// (bad magic, truncated input, unsupported compression).
+    pub fn from_reader<R: Read + Send + 'static>(reader: R) -> Self {
+        let htsfile = hfile::hts_open(hfile::from_reader(reader), b"r")
+            .expect("Could not detect BCF/VCF format of stream.");
+        Self::from_htsfile(htsfile)
+    }
+
+    fn from_htsfile(htsfile: *mut htslib::vcf::htsFile) -> Self {
         let header = unsafe { htslib::vcf::bcf_hdr_read(htsfile) };
         Reader { inner: htsfile, header: HeaderView::new(header) }
     }
 
+    /// Allocate a new record bound to this reader's header, suitable for
+    /// reuse across repeated `read()` calls.
+    pub fn empty_record(&self) -> record::Record {
+        let mut record = record::Record::new();
+        record.header = self.header.inner;
+        record
+    }
+
     pub fn read(&self, record: &mut record::Record) -> Result<(), ReadError> {
         match unsafe { htslib::vcf::bcf_read(self.inner, self.header.inner, record.inner) } {
             0  => {
@@ -48,6 +82,7 @@ impl Reader {
 
 impl Drop for Reader {
     fn drop(&mut self) {
+        header::forget_id_cache(self.header.inner);
         unsafe {
             htslib::vcf::bcf_hdr_destroy(self.header.inner);
             htslib::vcf::hts_close(self.inner);
@@ -68,14 +103,37 @@ unsafe impl Send for Writer {}
 
 impl Writer {
     pub fn new<P: AsRef<Path>>(path: &P, header: &Header, uncompressed: bool, vcf: bool) -> Self {
-        let mode: &[u8] = match (uncompressed, vcf) {
+        let htsfile = bcf_open(path, Self::mode(uncompressed, vcf));
+        Self::from_htsfile(htsfile, header)
+    }
+
+    /// Write to standard output instead of a file.
+    pub fn to_stdout(header: &Header, uncompressed: bool, vcf: bool) -> Self {
+        Self::from_writer(io::stdout(), header, uncompressed, vcf)
+    }
+
+    /// Write BCF/VCF to an arbitrary byte stream, without needing a
+    /// temporary file.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the requested mode could not be opened on the stream.
+    pub fn from_writer<W: Write + Send + 'static>(writer: W, header: &Header, uncompressed: bool, vcf: bool) -> Self {
+        let htsfile = hfile::hts_open(hfile::from_writer(writer), Self::mode(uncompressed, vcf))
+            .expect("Could not open stream for writing.");
+        Self::from_htsfile(htsfile, header)
+    }
+
+    fn mode(uncompressed: bool, vcf: bool) -> &'static [u8] {
+        match (uncompressed, vcf) {
             (true, true)   => b"w",
             (false, true)  => b"wz",
             (true, false)  => b"wu",
             (false, false) => b"wb",
-        };
+        }
+    }
 
-        let htsfile = bcf_open(path, mode);
+    fn from_htsfile(htsfile: *mut htslib::vcf::htsFile, header: &Header) -> Self {
         unsafe { htslib::vcf::bcf_hdr_write(htsfile, header.inner) };
         Writer {
             inner: htsfile,
@@ -115,6 +173,7 @@ impl Writer {
 
 impl Drop for Writer {
     fn drop(&mut self) {
+        header::forget_id_cache(self.header.inner);
         unsafe {
             htslib::vcf::bcf_hdr_destroy(self.header.inner);
             htslib::vcf::hts_close(self.inner);
@@ -163,6 +222,7 @@ pub enum ReadError {
 mod tests {
     extern crate tempdir;
     use super::*;
+    use std::fs::File;
     use std::path::Path;
 
     fn _test_read<P: AsRef<Path>>(path: &P) {
@@ -200,6 +260,110 @@ mod tests {
         _test_read(&"test.bcf");
     }
 
+    #[test]
+    fn test_header_metadata() {
+        let bcf = Reader::new(&"test.bcf");
+        let mq0f = bcf.header.info_type(b"MQ0F").expect("MQ0F should be declared in the header.");
+        assert_eq!(mq0f.tag_type, header::TagType::Float);
+        let pl = bcf.header.format_type(b"PL").expect("PL should be declared in the header.");
+        assert_eq!(pl.tag_type, header::TagType::Integer);
+        assert!(bcf.header.filter(b"PASS").is_some());
+        assert!(!bcf.header.contigs().is_empty());
+    }
+
+    #[test]
+    fn test_empty_record() {
+        let bcf = Reader::new(&"test.bcf");
+        let mut rec = bcf.empty_record();
+        let mut count = 0;
+        while bcf.read(&mut rec).is_ok() {
+            count += 1;
+        }
+        assert_eq!(count, 60);
+    }
+
+    #[test]
+    fn test_read_from_reader() {
+        let file = File::open("test.bcf").ok().expect("Error opening test.bcf");
+        let bcf = Reader::from_reader(file);
+        assert_eq!(bcf.header.samples(), [b"NA12878.subsample-0.25-0"]);
+        assert_eq!(bcf.records().count(), 60);
+    }
+
+    #[test]
+    fn test_write_read_through_streams() {
+        let bcf = Reader::new(&"test.bcf");
+        let header = Header::with_template(&bcf.header);
+
+        let tmp = tempdir::TempDir::new("rust-htslib").ok().expect("Cannot create temp dir");
+        let bcfpath = tmp.path().join("stream.bcf");
+        {
+            let file = File::create(&bcfpath).ok().expect("Error creating output file.");
+            let mut writer = Writer::from_writer(file, &header, false, false);
+            for rec in bcf.records() {
+                let mut record = rec.ok().expect("Error reading record.");
+                writer.translate(&mut record);
+                writer.write(&record).ok().expect("Error writing record.");
+            }
+        }
+        {
+            let file = File::open(&bcfpath).ok().expect("Error opening output file.");
+            let bcf = Reader::from_reader(file);
+            assert_eq!(bcf.header.samples(), [b"NA12878.subsample-0.25-0"]);
+            assert_eq!(bcf.records().count(), 60);
+        }
+        tmp.close().ok().expect("Failed to delete temp dir");
+    }
+
+    #[test]
+    fn test_record_filters() {
+        let bcf = Reader::new(&"test.bcf");
+        for rec in bcf.records() {
+            let record = rec.ok().expect("Error reading record.");
+            assert!(record.has_filter(b"PASS"));
+            assert_eq!(record.filters().count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_push_and_set_filters() {
+        let bcf = Reader::new(&"test.bcf");
+        let mut header = Header::with_template(&bcf.header);
+        header.push_record(b"##FILTER=<ID=q10,Description=\"Quality below 10\">");
+        header.push_record(b"##FILTER=<ID=s50,Description=\"Less than 50% of samples have data\">");
+
+        let tmp = tempdir::TempDir::new("rust-htslib").ok().expect("Cannot create temp dir");
+        let bcfpath = tmp.path().join("filtered.bcf");
+        {
+            let mut writer = Writer::new(&bcfpath, &header, false, false);
+
+            let mut pushed = bcf.records().next().unwrap().ok().expect("Error reading record.");
+            writer.translate(&mut pushed);
+            pushed.push_filter(b"q10").ok().expect("Error pushing filter.");
+            writer.write(&pushed).ok().expect("Error writing record.");
+
+            let mut set = bcf.records().nth(1).unwrap().ok().expect("Error reading record.");
+            writer.translate(&mut set);
+            set.set_filters(&[b"q10", b"s50"]).ok().expect("Error setting filters.");
+            writer.write(&set).ok().expect("Error writing record.");
+        }
+        {
+            let bcf = Reader::new(&bcfpath);
+            let mut records = bcf.records();
+
+            let pushed = records.next().unwrap().ok().expect("Error reading record.");
+            assert!(pushed.has_filter(b"q10"));
+            assert!(!pushed.has_filter(b"PASS"));
+            let ids: Vec<_> = pushed.filters().map(|id| bcf.header.id_to_name(id).expect("Unknown filter id.")).collect();
+            assert_eq!(ids, [b"q10".to_vec()]);
+
+            let set = records.next().unwrap().ok().expect("Error reading record.");
+            let ids: Vec<_> = set.filters().map(|id| bcf.header.id_to_name(id).expect("Unknown filter id.")).collect();
+            assert_eq!(ids, [b"q10".to_vec(), b"s50".to_vec()]);
+        }
+        tmp.close().ok().expect("Failed to delete temp dir");
+    }
+
     #[test]
     fn test_write() {
         let bcf = Reader::new(&"test_multi.bcf");
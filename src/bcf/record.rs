@@ -0,0 +1,299 @@
+// Copyright 2014 Johannes Köster.
+// Licensed under the MIT license (http://opensource.org/licenses/MIT)
+// This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+extern crate libc;
+
+use std::ffi;
+use std::ptr;
+use std::slice;
+
+use htslib;
+use bcf::header::HeaderView;
+
+
+/// A BCF/VCF record.
+pub struct Record {
+    pub inner: *mut htslib::vcf::bcf1_t,
+    pub header: *mut htslib::vcf::bcf_hdr_t,
+}
+
+
+impl Record {
+    /// Create a new, empty record. Usually obtained via
+    /// `Reader::empty_record()` instead, so that `header` is already bound.
+    pub fn new() -> Self {
+        Record {
+            inner: unsafe { htslib::vcf::bcf_init() },
+            header: ptr::null_mut(),
+        }
+    }
+
+    fn header(&self) -> HeaderView {
+        HeaderView::new(self.header)
+    }
+
+    #[inline]
+    fn inner(&self) -> htslib::vcf::bcf1_t {
+        unsafe { *self.inner }
+    }
+
+    /// The reference sequence id of this record, or `None` if unset.
+    pub fn rid(&self) -> Option<u32> {
+        match self.inner().rid {
+            -1  => None,
+            rid => Some(rid as u32),
+        }
+    }
+
+    /// The 0-based position of this record.
+    pub fn pos(&self) -> u32 {
+        self.inner().pos as u32
+    }
+
+    /// The QUAL value of this record.
+    pub fn qual(&self) -> f32 {
+        self.inner().qual
+    }
+
+    /// The number of samples carried by this record.
+    pub fn sample_count(&self) -> u32 {
+        unsafe { (*self.inner).n_sample() }
+    }
+
+    /// The number of alleles (reference plus alternates) of this record.
+    pub fn allele_count(&self) -> u32 {
+        unsafe { (*self.inner).n_allele() }
+    }
+
+    /// The alleles of this record, reference first.
+    pub fn alleles(&self) -> Vec<&[u8]> {
+        unsafe { htslib::vcf::bcf_unpack(self.inner, htslib::vcf::BCF_UN_STR as i32) };
+        let n = self.allele_count() as usize;
+        let alleles = unsafe { slice::from_raw_parts(self.inner().d.allele, n) };
+        alleles.iter().map(|&a| unsafe { ffi::CStr::from_ptr(a).to_bytes() }).collect()
+    }
+
+    /// Access the value of an INFO tag.
+    pub fn info<'a>(&'a self, tag: &'a [u8]) -> Info<'a> {
+        Info { record: self, tag: tag }
+    }
+
+    /// Access the per-sample values of a FORMAT tag.
+    pub fn format<'a>(&'a self, tag: &'a [u8]) -> Format<'a> {
+        Format { record: self, tag: tag }
+    }
+
+    /// Remove alleles (and shrink genotype-indexed FORMAT fields accordingly)
+    /// that are no longer referenced by any sample's genotype.
+    pub fn trim_alleles(&mut self) -> Result<(), ()> {
+        if unsafe { htslib::vcf::bcf_trim_alleles(self.header, self.inner) } < 0 {
+            Err(())
+        }
+        else {
+            Ok(())
+        }
+    }
+
+    /// Iterate over the dictionary ids of the FILTERs set on this record.
+    pub fn filters(&self) -> Filters {
+        unsafe { htslib::vcf::bcf_unpack(self.inner, htslib::vcf::BCF_UN_FLT as i32) };
+        Filters { record: self, i: 0 }
+    }
+
+    /// Check whether `id` (e.g. `b"PASS"`) is among the FILTERs set on this record.
+    /// A record with no FILTERs set (`.`) also counts as having `PASS`.
+    pub fn has_filter(&self, id: &[u8]) -> bool {
+        let c_str = ffi::CString::new(id).unwrap();
+        unsafe { htslib::vcf::bcf_has_filter(self.header, self.inner, c_str.as_ptr() as *mut i8) == 1 }
+    }
+
+    /// Replace the FILTERs set on this record with `ids`, resolving each
+    /// through the header dictionary.
+    pub fn set_filters(&mut self, ids: &[&[u8]]) -> Result<(), ()> {
+        let header = self.header();
+        let mut int_ids = Vec::with_capacity(ids.len());
+        for &id in ids {
+            match header.name_to_id(id) {
+                Some(int_id) => int_ids.push(int_id),
+                None         => return Err(()),
+            }
+        }
+        let ret = unsafe {
+            htslib::vcf::bcf_update_filter(self.header, self.inner, int_ids.as_mut_ptr(), int_ids.len() as i32)
+        };
+        if ret < 0 {
+            Err(())
+        }
+        else {
+            Ok(())
+        }
+    }
+
+    /// Add `id` to the FILTERs set on this record, resolving it through the
+    /// header dictionary.
+    pub fn push_filter(&mut self, id: &[u8]) -> Result<(), ()> {
+        match self.header().name_to_id(id) {
+            Some(int_id) => {
+                if unsafe { htslib::vcf::bcf_add_filter(self.header, self.inner, int_id) } < 0 {
+                    Err(())
+                }
+                else {
+                    Ok(())
+                }
+            },
+            None => Err(()),
+        }
+    }
+}
+
+
+impl Drop for Record {
+    fn drop(&mut self) {
+        unsafe { htslib::vcf::bcf_destroy(self.inner) };
+    }
+}
+
+
+pub struct Filters<'a> {
+    record: &'a Record,
+    i: i32,
+}
+
+
+impl<'a> Iterator for Filters<'a> {
+    type Item = i32;
+
+    fn next(&mut self) -> Option<i32> {
+        let inner = unsafe { *self.record.inner };
+        if self.i >= inner.d.n_flt {
+            None
+        }
+        else {
+            let id = unsafe { *inner.d.flt.offset(self.i as isize) };
+            self.i += 1;
+            Some(id)
+        }
+    }
+}
+
+
+/// Access to the values of an INFO tag.
+pub struct Info<'a> {
+    record: &'a Record,
+    tag: &'a [u8],
+}
+
+
+impl<'a> Info<'a> {
+    fn values(&mut self, value_type: i32) -> Result<(*mut libc::c_void, i32), ()> {
+        let c_tag = ffi::CString::new(self.tag).unwrap();
+        let mut buf: *mut libc::c_void = ptr::null_mut();
+        let mut n: i32 = 0;
+        let ret = unsafe {
+            htslib::vcf::bcf_get_info_values(self.record.header, self.record.inner, c_tag.as_ptr(), &mut buf, &mut n, value_type)
+        };
+        if ret < 0 {
+            unsafe { libc::free(buf) };
+            Err(())
+        }
+        else {
+            Ok((buf, ret))
+        }
+    }
+
+    /// Read the INFO tag as a vector of integers.
+    pub fn integer(&mut self) -> Result<Vec<i32>, ()> {
+        match self.values(htslib::vcf::BCF_HT_INT as i32) {
+            Ok((buf, n)) => {
+                let values = unsafe { slice::from_raw_parts(buf as *const i32, n as usize) }.to_vec();
+                unsafe { libc::free(buf) };
+                Ok(values)
+            },
+            Err(()) => Err(()),
+        }
+    }
+
+    /// Read the INFO tag as a vector of floats.
+    pub fn float(&mut self) -> Result<Vec<f32>, ()> {
+        match self.values(htslib::vcf::BCF_HT_REAL as i32) {
+            Ok((buf, n)) => {
+                let values = unsafe { slice::from_raw_parts(buf as *const f32, n as usize) }.to_vec();
+                unsafe { libc::free(buf) };
+                Ok(values)
+            },
+            Err(()) => Err(()),
+        }
+    }
+
+    /// Check whether this (flag-typed) INFO tag is set.
+    pub fn flag(&mut self) -> bool {
+        let c_tag = ffi::CString::new(self.tag).unwrap();
+        let mut buf: *mut libc::c_void = ptr::null_mut();
+        let mut n: i32 = 0;
+        let ret = unsafe {
+            htslib::vcf::bcf_get_info_values(self.record.header, self.record.inner, c_tag.as_ptr(), &mut buf, &mut n, htslib::vcf::BCF_HT_FLAG as i32)
+        };
+        unsafe { libc::free(buf) };
+        ret == 1
+    }
+}
+
+
+/// Access to the per-sample values of a FORMAT tag.
+pub struct Format<'a> {
+    record: &'a Record,
+    tag: &'a [u8],
+}
+
+
+impl<'a> Format<'a> {
+    fn values(&mut self, value_type: i32) -> Result<(*mut libc::c_void, i32), ()> {
+        let c_tag = ffi::CString::new(self.tag).unwrap();
+        let mut buf: *mut libc::c_void = ptr::null_mut();
+        let mut n: i32 = 0;
+        let ret = unsafe {
+            htslib::vcf::bcf_get_format_values(self.record.header, self.record.inner, c_tag.as_ptr(), &mut buf, &mut n, value_type)
+        };
+        if ret < 0 {
+            unsafe { libc::free(buf) };
+            Err(())
+        }
+        else {
+            Ok((buf, ret))
+        }
+    }
+
+    fn per_sample<T: Copy>(&self, buf: *const T, n: i32) -> Vec<Vec<T>> {
+        let n_sample = self.record.sample_count() as usize;
+        let stride = if n_sample > 0 { n as usize / n_sample } else { 0 };
+        let values = unsafe { slice::from_raw_parts(buf, n as usize) };
+        values.chunks(stride).map(|chunk| chunk.to_vec()).collect()
+    }
+
+    /// Read the FORMAT tag as one vector of integers per sample.
+    pub fn integer(&mut self) -> Result<Vec<Vec<i32>>, ()> {
+        match self.values(htslib::vcf::BCF_HT_INT as i32) {
+            Ok((buf, n)) => {
+                let values = self.per_sample(buf as *const i32, n);
+                unsafe { libc::free(buf) };
+                Ok(values)
+            },
+            Err(()) => Err(()),
+        }
+    }
+
+    /// Read the FORMAT tag as one vector of floats per sample.
+    pub fn float(&mut self) -> Result<Vec<Vec<f32>>, ()> {
+        match self.values(htslib::vcf::BCF_HT_REAL as i32) {
+            Ok((buf, n)) => {
+                let values = self.per_sample(buf as *const f32, n);
+                unsafe { libc::free(buf) };
+                Ok(values)
+            },
+            Err(()) => Err(()),
+        }
+    }
+}